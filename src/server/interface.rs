@@ -5,6 +5,8 @@ use capnp::capability::Promise;
 use std::net::SocketAddr;
 use capnp;
 
+use common::convert::{FromCapnp, ToCapnp};
+
 
 use server::client_srv::ClientServiceImpl;
 use server::upstream::WorkerUpstreamImpl;
@@ -19,6 +21,12 @@ pub struct ServerBootstrapImpl {
     state: State,
     registered: bool,
     address: SocketAddr,
+
+    /// Set once this connection registers as a worker, so the entry can be
+    /// evicted from the server's worker table when the connection drops
+    /// instead of lingering as a stale registration a reconnect would
+    /// otherwise be rejected in favour of.
+    worker_id: Option<SocketAddr>,
 }
 
 impl ServerBootstrapImpl {
@@ -27,6 +35,7 @@ impl ServerBootstrapImpl {
             state: state.clone(),
             registered: false,
             address: address,
+            worker_id: None,
         }
     }
 }
@@ -34,6 +43,10 @@ impl ServerBootstrapImpl {
 impl Drop for ServerBootstrapImpl {
     fn drop(&mut self) {
         debug!("ServerBootstrap dropped {}", self.address);
+        if let Some(worker_id) = self.worker_id {
+            info!("Removing worker {} (connection {} closed)", worker_id, self.address);
+            self.state.remove_worker(&worker_id);
+        }
     }
 }
 
@@ -91,10 +104,34 @@ impl server_bootstrap::Server for ServerBootstrapImpl {
             return Promise::err(capnp::Error::failed(format!("Protocol mismatch")));
         }
 
-        self.registered = true;
+        // The worker advertises the address it can be reached at for
+        // worker2worker transfers (corrected for its actual bound port, see
+        // `worker::StateRef::start`). Use that as its identity so peers can
+        // dial it directly; only fall back to a server-assigned id when the
+        // advertised address isn't one the server could hand out to a peer.
+        // The fallback is keyed by the connecting peer's IP (not a bare
+        // counter) so a worker behind such an address keeps the same id
+        // across chunk0-4 reconnects instead of being issued a new one
+        // every time — though, unlike a routable advertised address, it is
+        // still not one a peer could actually dial.
+        let advertised = SocketAddr::from_capnp(&pry!(params.get_address()));
+        let worker_id = if is_routable(&advertised) {
+            advertised
+        } else {
+            self.state.next_worker_id(self.address.ip())
+        };
+
+        if self.state.has_worker(&worker_id) {
+            error!("Connection {} tried to register as already-known worker {}",
+                   self.address,
+                   worker_id);
+            return Promise::err(capnp::Error::failed(
+                format!("Worker {} is already registered", worker_id),
+            ));
+        }
 
-        let mut worker_id = self.address;
-        worker_id.set_port(1234); // TODO
+        self.registered = true;
+        self.worker_id = Some(worker_id);
 
         info!("Connection {} registered as worker {}", self.address, worker_id);
 
@@ -107,6 +144,36 @@ impl server_bootstrap::Server for ServerBootstrapImpl {
         ).from_server::<::capnp_rpc::Server>();
 
         results.get().set_upstream(upstream);
+        worker_id.to_capnp(&mut results.get().get_worker_id().unwrap());
         Promise::ok(())
     }
 }
+
+/// A worker-advertised address is only useful as its identity if it is one
+/// a peer could actually dial: a real port, and not a wildcard/unspecified
+/// host address.
+fn is_routable(address: &SocketAddr) -> bool {
+    address.port() != 0 && !address.ip().is_unspecified()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unspecified_host() {
+        assert!(!is_routable(&"0.0.0.0:1234".parse().unwrap()));
+        assert!(!is_routable(&"[::]:1234".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_port_zero() {
+        assert!(!is_routable(&"127.0.0.1:0".parse().unwrap()));
+    }
+
+    #[test]
+    fn accepts_a_real_dialable_address() {
+        assert!(is_routable(&"127.0.0.1:1234".parse().unwrap()));
+        assert!(is_routable(&"10.0.0.5:4300".parse().unwrap()));
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+use common::wrapped::WrappedRcRefCell;
+use server::worker::Worker;
+
+pub struct StateInner {
+    /// Workers currently registered with the server, keyed by their
+    /// `WorkerId` (the address they advertised, or a synthetic stand-in;
+    /// see `State::next_worker_id`).
+    workers: HashMap<SocketAddr, Worker>,
+
+    /// Synthetic `WorkerId`s handed out to workers whose advertised listen
+    /// address isn't routable, keyed by the connecting peer's IP so a
+    /// worker reconnecting from the same host gets the same id back
+    /// instead of a fresh one on every attempt.
+    fallback_worker_ids: HashMap<IpAddr, SocketAddr>,
+
+    next_fallback_port: u16,
+}
+
+pub type State = WrappedRcRefCell<StateInner>;
+
+impl StateInner {
+    fn new() -> Self {
+        StateInner {
+            workers: HashMap::new(),
+            fallback_worker_ids: HashMap::new(),
+            next_fallback_port: 1,
+        }
+    }
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self::wrap(StateInner::new())
+    }
+
+    pub fn add_worker(&self, worker: Worker) {
+        let id = worker.id();
+        self.get_mut().workers.insert(id, worker);
+    }
+
+    pub fn has_worker(&self, worker_id: &SocketAddr) -> bool {
+        self.get().workers.contains_key(worker_id)
+    }
+
+    pub fn remove_worker(&self, worker_id: &SocketAddr) {
+        self.get_mut().workers.remove(worker_id);
+    }
+
+    /// Hands out a `WorkerId` for a worker whose advertised listen address
+    /// isn't routable (see `server::interface::is_routable`). The id is a
+    /// synthetic, locally-scoped address rather than one a peer could dial
+    /// — but it is stable per source IP, so a worker reconnecting from the
+    /// same host keeps the same id instead of being issued a new one every
+    /// time (losing its old id would defeat chunk0-4's reconnect handling).
+    pub fn next_worker_id(&self, source_ip: IpAddr) -> SocketAddr {
+        let mut inner = self.get_mut();
+        if let Some(&id) = inner.fallback_worker_ids.get(&source_ip) {
+            return id;
+        }
+
+        let port = inner.next_fallback_port;
+        inner.next_fallback_port = inner.next_fallback_port.wrapping_add(1);
+        let id = SocketAddr::new(source_ip, port);
+        inner.fallback_worker_ids.insert(source_ip, id);
+        id
+    }
+}
@@ -1,11 +1,11 @@
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
-use std::process::exit;
 use std::path::{Path, PathBuf};
-use std::io::Write;
+use std::io::{Read, Write};
 use std::time::Duration;
 use std::iter::FromIterator;
 use std::error::Error;
@@ -15,6 +15,7 @@ use common::RcSet;
 use common::id::{SubworkerId, SessionId, WorkerId, empty_worker_id, Id, TaskId, DataObjectId};
 use common::convert::{ToCapnp, FromCapnp};
 use common::rpc::new_rpc_system;
+use common::transport::{self, Transport};
 use common::keeppolicy::KeepPolicy;
 use common::wrapped::WrappedRcRefCell;
 use common::resources::Resources;
@@ -22,19 +23,37 @@ use worker::graph::{DataObjectRef, DataObjectType, DataObjectState,
                     Graph, TaskRef, TaskInput, SubworkerRef, start_python_subworker};
 use worker::rpc::{SubworkerUpstreamImpl, WorkerControlImpl};
 
-use futures::Future;
+use futures::{Async, Future, Poll};
 use futures::Stream;
+use futures::task::{self, Task};
 use tokio_core::reactor::Handle;
 use tokio_core::net::TcpListener;
 use tokio_core::net::TcpStream;
-use tokio_io::AsyncRead;
+use tokio_io::{AsyncRead, AsyncWrite};
 use tokio_timer;
 use tokio_uds::{UnixListener, UnixStream};
+use rand::Rng;
 use capnp_rpc::{RpcSystem, twoparty, rpc_twoparty_capnp};
 use capnp::capability::Promise;
 
 use WORKER_PROTOCOL_VERSION;
 
+/// Default ceiling on the number of simultaneously open subworker/peer
+/// connections before new ones stop being accepted.
+const DEFAULT_MAXCONN: usize = 1024;
+
+/// How far below `maxconn` the live count has to drop before accepting is
+/// resumed. Keeps a flood of connections closing at once from immediately
+/// re-triggering the high watermark.
+const LOW_WATERMARK_MARGIN: usize = 64;
+
+/// Starting delay for the exponential backoff used when (re)connecting to
+/// the server.
+const RECONNECT_BASE_DELAY_MS: u64 = 100;
+
+/// Ceiling the backoff delay never grows past.
+const RECONNECT_MAX_DELAY_MS: u64 = 30_000;
+
 pub struct State {
     graph: Graph,
 
@@ -53,10 +72,285 @@ pub struct State {
     work_dir: PathBuf,
 
     resources: Resources,
+
+    /// Outbound RPC connections towards other workers, keyed by their
+    /// worker2worker address, so that fetching several data objects from the
+    /// same peer reuses a live session instead of reconnecting each time.
+    peer_pools: HashMap<SocketAddr, Rc<RefCell<PeerConnPool>>>,
+
+    /// Admission-control state shared by the subworker and worker2worker
+    /// accept loops.
+    admission: Rc<Admission>,
+
+    /// Transport used for worker↔server and worker↔worker RPC, chosen by
+    /// the scheme of the configured addresses (`tcp://` or `quic://`).
+    transport: Transport,
+
+    /// Current backoff delay before the next server reconnection attempt;
+    /// doubles on every failed attempt and resets once registration with
+    /// the server completes.
+    reconnect_delay_ms: Cell<u64>,
+
+    /// Set while a reconnect is already scheduled for the current
+    /// connection attempt, so that the registration future and the raw
+    /// `RpcSystem` future erroring out on the same dead socket don't each
+    /// schedule their own reconnect. Cleared once the next attempt starts.
+    reconnect_scheduled: Cell<bool>,
 }
 
 pub type StateRef = WrappedRcRefCell<State>;
 
+/// Tracks how many connections are currently open against `maxconn`/
+/// `low_watermark`, and parks the accept task while at capacity so it can be
+/// woken back up once a connection closes and the count drops low enough.
+struct Admission {
+    maxconn: usize,
+    low_watermark: usize,
+    count: Cell<usize>,
+    /// One slot per accept loop sharing this `Admission` (the subworker and
+    /// worker2worker listeners both throttle off the same connection
+    /// count), so parking one doesn't evict another's stashed `Task`.
+    parked: RefCell<Vec<Task>>,
+}
+
+impl Admission {
+    fn new(maxconn: usize) -> Self {
+        Admission {
+            maxconn,
+            low_watermark: maxconn.saturating_sub(LOW_WATERMARK_MARGIN),
+            count: Cell::new(0),
+            parked: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn at_capacity(&self) -> bool {
+        self.count.get() >= self.maxconn
+    }
+
+    fn park(&self) {
+        self.parked.borrow_mut().push(task::current());
+    }
+
+    fn inc(&self) {
+        self.count.set(self.count.get() + 1);
+    }
+
+    fn dec(&self) {
+        let n = self.count.get() - 1;
+        self.count.set(n);
+        if n <= self.low_watermark {
+            for task in self.parked.borrow_mut().drain(..) {
+                task.notify();
+            }
+        }
+    }
+}
+
+/// Wraps a listener's `incoming()` stream so it stops being polled (instead
+/// of busy-accepting) once `admission` is at capacity, and resumes once a
+/// closed connection brings the live count back down to the low watermark.
+struct ThrottledIncoming<S> {
+    inner: S,
+    admission: Rc<Admission>,
+}
+
+impl<S: Stream> Stream for ThrottledIncoming<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if self.admission.at_capacity() {
+            self.admission.park();
+            return Ok(Async::NotReady);
+        }
+        self.inner.poll()
+    }
+}
+
+/// Wraps a per-connection future so `admission`'s live count is decremented
+/// exactly once when the connection ends, however it ends: a clean finish,
+/// an RPC error, or the peer simply disconnecting and dropping the future.
+struct CountedConn<F> {
+    inner: F,
+    admission: Rc<Admission>,
+}
+
+impl<F: Future> Future for CountedConn<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        self.inner.poll()
+    }
+}
+
+impl<F> Drop for CountedConn<F> {
+    fn drop(&mut self) {
+        self.admission.dec();
+    }
+}
+
+/// A pool of idle RPC sessions towards a single peer worker.
+struct PeerConnPool {
+    idle: VecDeque<PeerConn>,
+
+    /// When talking QUIC to this peer, the single underlying connection is
+    /// kept here and reused: each checkout just opens a fresh bidirectional
+    /// stream on it instead of paying for a new handshake.
+    quic_conn: Option<transport::QuicConnection>,
+}
+
+impl PeerConnPool {
+    fn new() -> Self {
+        PeerConnPool {
+            idle: VecDeque::new(),
+            quic_conn: None,
+        }
+    }
+
+    /// Pops idle connections until a healthy one is found (discarding any
+    /// that went bad while sitting in the queue), or the pool runs dry.
+    fn checkout(&mut self) -> Option<PeerConn> {
+        while let Some(conn) = self.idle.pop_front() {
+            if conn.healthy.get() {
+                return Some(conn);
+            }
+        }
+        None
+    }
+}
+
+/// A stream carrying Cap'n Proto RPC traffic over whichever transport was
+/// negotiated for the connection.
+enum RpcStream {
+    Tcp(TcpStream),
+    Quic(transport::QuicStream),
+}
+
+impl Read for RpcStream {
+    fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+        match *self {
+            RpcStream::Tcp(ref mut s) => s.read(buf),
+            RpcStream::Quic(ref mut s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for RpcStream {
+    fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+        match *self {
+            RpcStream::Tcp(ref mut s) => s.write(buf),
+            RpcStream::Quic(ref mut s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> ::std::io::Result<()> {
+        match *self {
+            RpcStream::Tcp(ref mut s) => s.flush(),
+            RpcStream::Quic(ref mut s) => s.flush(),
+        }
+    }
+}
+
+impl AsyncRead for RpcStream {}
+
+impl AsyncWrite for RpcStream {
+    fn shutdown(&mut self) -> Poll<(), ::std::io::Error> {
+        match *self {
+            RpcStream::Tcp(ref mut s) => AsyncWrite::shutdown(s),
+            RpcStream::Quic(ref mut s) => AsyncWrite::shutdown(s),
+        }
+    }
+}
+
+/// Dials `addr` over the given transport, yielding a stream that can be fed
+/// straight into `common::rpc::new_rpc_system` like the plain TCP path
+/// always has.
+fn connect_rpc_stream(transport: Transport,
+                      addr: &SocketAddr,
+                      handle: &Handle)
+                      -> Box<Future<Item = RpcStream, Error = ::std::io::Error>> {
+    match transport {
+        Transport::Tcp => Box::new(TcpStream::connect(addr, handle).map(RpcStream::Tcp)),
+        Transport::Quic => Box::new(transport::connect(handle, addr).map(RpcStream::Quic)),
+    }
+}
+
+/// A single RPC session towards a peer worker. `healthy` is shared with the
+/// future driving the underlying `RpcSystem` and is cleared if that system
+/// ever reports an error, so a broken session is never handed out again.
+struct PeerConn {
+    client: ::worker_capnp::data_transfer::Client,
+    healthy: Rc<Cell<bool>>,
+}
+
+/// RAII guard around a checked-out peer connection. Returns the connection
+/// to its pool on drop, unless the RPC session went bad while it was held.
+struct PooledPeerConn {
+    pool: Rc<RefCell<PeerConnPool>>,
+    conn: Option<PeerConn>,
+}
+
+impl PooledPeerConn {
+    fn client(&self) -> &::worker_capnp::data_transfer::Client {
+        &self.conn.as_ref().unwrap().client
+    }
+
+    fn healthy_handle(&self) -> Rc<Cell<bool>> {
+        self.conn.as_ref().unwrap().healthy.clone()
+    }
+}
+
+impl Drop for PooledPeerConn {
+    fn drop(&mut self) {
+        let conn = self.conn.take().unwrap();
+        if conn.healthy.get() {
+            self.pool.borrow_mut().idle.push_back(conn);
+        }
+    }
+}
+
+/// Serves data object bytes to peer workers fetching over worker2worker RPC.
+struct DataTransferImpl {
+    state: StateRef,
+}
+
+impl DataTransferImpl {
+    fn new(state: &StateRef) -> Self {
+        DataTransferImpl { state: state.clone() }
+    }
+}
+
+impl ::worker_capnp::data_transfer::Server for DataTransferImpl {
+    fn get_data_object(&mut self,
+                       params: ::worker_capnp::data_transfer::GetDataObjectParams,
+                       mut results: ::worker_capnp::data_transfer::GetDataObjectResults)
+                       -> Promise<(), ::capnp::Error> {
+        let params = pry!(params.get());
+        let id = DataObjectId::from_capnp(&pry!(params.get_id()));
+
+        let state = self.state.get();
+        let object = match state.graph.objects.get(&id) {
+            Some(object) => object.clone(),
+            None => {
+                return Promise::err(capnp::Error::failed(
+                    format!("Data object {} not found on this worker", id)));
+            }
+        };
+
+        let data = match object.get().data() {
+            Some(data) => data,
+            None => {
+                return Promise::err(capnp::Error::failed(
+                    format!("Data object {} is not yet finished", id)));
+            }
+        };
+
+        results.get().set_data(&data);
+        Promise::ok(())
+    }
+}
+
 impl State {
 
     pub fn make_subworker_id(&mut self) -> SubworkerId {
@@ -153,7 +447,7 @@ impl State {
 }
 
 impl StateRef {
-    pub fn new(handle: Handle, work_dir: PathBuf, n_cpus: u32) -> Self {
+    pub fn new(handle: Handle, work_dir: PathBuf, n_cpus: u32, transport: Transport) -> Self {
         Self::wrap(State {
                        handle,
                        resources: Resources {n_cpus},
@@ -165,43 +459,141 @@ impl StateRef {
                        work_dir,
                        worker_id: empty_worker_id(),
                        graph: Graph::new(),
+                       peer_pools: HashMap::new(),
+                       admission: Rc::new(Admission::new(DEFAULT_MAXCONN)),
+                       transport,
+                       reconnect_delay_ms: Cell::new(RECONNECT_BASE_DELAY_MS),
+                       reconnect_scheduled: Cell::new(false),
                    })
     }
 
-    // This is called when an incomming connection arrives
-    fn on_connection(&self, stream: TcpStream, address: SocketAddr) {
-        // Handle an incoming connection; spawn gate object for it
+    // This is called when an incomming connection arrives from another worker
+    fn on_connection(&self, stream: RpcStream, address: SocketAddr) {
+        // Handle an incoming connection; spawn a data-transfer gate for it
+        // so the peer can fetch data objects that live on this worker.
 
-        info!("New connection from {}", address);
-        stream.set_nodelay(true).unwrap();
-        let (reader, writer) = stream.split();
+        info!("New worker2worker connection from {}", address);
 
-        panic!("Not implemented yet");
-        /*
-        let bootstrap_obj = ::server_capnp::server_bootstrap::ToClient::new(
-            ServerBootstrapImpl::new(self, address),
+        let bootstrap = ::worker_capnp::data_transfer::ToClient::new(
+            DataTransferImpl::new(self),
         ).from_server::<::capnp_rpc::Server>();
 
-        let network = twoparty::VatNetwork::new(
-            reader,
-            writer,
-            rpc_twoparty_capnp::Side::Server,
-            Default::default(),
-        );
+        let admission = self.get().admission.clone();
+        admission.inc();
+        let rpc_system = new_rpc_system(stream, Some(bootstrap.client));
+        let conn = CountedConn {
+            inner: rpc_system.map_err(move |e| {
+                error!("Worker2worker RPC error with {}: {:?}", address, e)
+            }),
+            admission,
+        };
+        self.get().handle.spawn(conn);
+    }
+
+    /// Returns a pooled RPC session towards `address`, reusing an idle
+    /// connection from a previous fetch if the pool for that peer has one.
+    /// Otherwise it dials a new `TcpStream`, or over QUIC opens a new stream
+    /// on the peer's pooled QUIC connection (connecting first if there is none).
+    fn checkout_peer_conn(&self,
+                          address: SocketAddr)
+                          -> Box<Future<Item = PooledPeerConn, Error = ::std::io::Error>> {
+        let pool = self.get_mut()
+            .peer_pools
+            .entry(address)
+            .or_insert_with(|| Rc::new(RefCell::new(PeerConnPool::new())))
+            .clone();
+
+        if let Some(conn) = pool.borrow_mut().checkout() {
+            return Box::new(::futures::future::ok(PooledPeerConn {
+                                                       pool,
+                                                       conn: Some(conn),
+                                                   }));
+        }
+
+        let transport = self.get().transport;
+        let handle = self.get().handle.clone();
+        let handle2 = handle.clone();
+        let pool2 = pool.clone();
+
+        // For QUIC, a single connection per peer is kept in the pool and
+        // every checkout just opens a fresh bidirectional stream on it, so
+        // only the very first fetch to a peer pays for a handshake.
+        let stream_future: Box<Future<Item = RpcStream, Error = ::std::io::Error>> =
+            match (transport, pool.borrow().quic_conn.clone()) {
+                (Transport::Quic, Some(conn)) => Box::new(conn.open_bi().map(RpcStream::Quic)),
+                (Transport::Quic, None) => {
+                    Box::new(transport::open_connection(&handle, &address).and_then(move |conn| {
+                        let bi = conn.open_bi();
+                        pool2.borrow_mut().quic_conn = Some(conn);
+                        bi
+                    }).map(RpcStream::Quic))
+                }
+                (Transport::Tcp, _) => connect_rpc_stream(Transport::Tcp, &address, &handle),
+            };
+
+        Box::new(stream_future.map(move |stream| {
+            if let RpcStream::Tcp(ref s) = stream {
+                s.set_nodelay(true).unwrap();
+            }
+            let mut rpc_system = new_rpc_system(stream, None);
+            let client: ::worker_capnp::data_transfer::Client =
+                rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+
+            let healthy = Rc::new(Cell::new(true));
+            let healthy2 = healthy.clone();
+            handle2.spawn(rpc_system.map_err(move |e| {
+                error!("Peer connection RPC error with {}: {:?}", address, e);
+                healthy2.set(false);
+            }));
+
+            PooledPeerConn {
+                pool,
+                conn: Some(PeerConn { client, healthy }),
+            }
+        }))
+    }
 
-        let rpc_system = RpcSystem::new(Box::new(network), Some(bootstrap_obj.client));
-        self.inner.borrow().handle.spawn(rpc_system.map_err(|e| {
-            panic!("RPC error: {:?}", e)
-        }));*/
+    /// Fetches the bytes of data object `id` from the worker listening at
+    /// `address`, reusing a pooled connection to that peer when possible.
+    pub fn fetch_data_object(&self,
+                             address: SocketAddr,
+                             id: DataObjectId)
+                             -> Box<Future<Item = Vec<u8>, Error = ::capnp::Error>> {
+        Box::new(self.checkout_peer_conn(address)
+                     .map_err(|e| capnp::Error::failed(format!("Connection failed: {}", e)))
+                     .and_then(move |conn| {
+            let healthy = conn.healthy_handle();
+            let mut req = conn.client().get_data_object_request();
+            id.to_capnp(&mut req.get().get_id().unwrap());
+            req.send().promise.and_then(move |response| {
+                let data = response.get()?.get_data()?.to_vec();
+                // `conn` is kept alive until here so it is only returned to
+                // the pool once the response has been fully read.
+                drop(conn);
+                Ok(data)
+            }).map_err(move |e| {
+                // A failed request on a connection we were actively using
+                // means it is dead right now — mark it unhealthy
+                // synchronously instead of waiting for the separately
+                // spawned `rpc_system` future to notice and do it later,
+                // by which point this same connection may already have
+                // been checked out again and handed back out dead.
+                healthy.set(false);
+                e
+            })
+        }))
     }
 
     // This is called when worker connection to server is established
     pub fn on_connected_to_server(&self,
-                                  stream: TcpStream,
+                                  stream: RpcStream,
+                                  server_address: SocketAddr,
                                   listen_address: SocketAddr,
                                   ready_file: Option<String>) {
         info!("Connected to server; registering as worker");
-        stream.set_nodelay(true).unwrap();
+        if let RpcStream::Tcp(ref s) = stream {
+            s.set_nodelay(true).unwrap();
+        }
         let mut rpc_system = ::common::rpc::new_rpc_system(stream, None);
         let bootstrap: ::server_capnp::server_bootstrap::Client =
             rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
@@ -216,7 +608,10 @@ impl StateRef {
         req.get().set_control(worker_control);
         listen_address.to_capnp(&mut req.get().get_address().unwrap());
 
+        let ready_file_for_rpc = ready_file.clone();
         let state = self.clone();
+        let state_err = self.clone();
+        let ready_file_err = ready_file.clone();
         let future = req.send()
             .promise
             .and_then(move |response| {
@@ -233,17 +628,25 @@ impl StateRef {
                     ::common::fs::create_ready_file(Path::new(&name));
                 }
 
+                state.reset_reconnect_backoff();
                 Promise::ok(())
             })
-            .map_err(|e| {
-                         panic!("Error {}", e);
-                     });
+            .map_err(move |e| {
+                error!("Registration with server failed: {}", e);
+                state_err.schedule_reconnect(server_address, listen_address, ready_file_err);
+            });
 
         let inner = self.get();
         inner.handle.spawn(future);
-        inner
-            .handle
-            .spawn(rpc_system.map_err(|e| error!("RPC error: {:?}", e)));
+
+        let state_rpc = self.clone();
+        let ready_file_rpc = ready_file_for_rpc;
+        inner.handle.spawn(rpc_system.map_err(move |e| {
+            error!("RPC error: {:?}", e);
+            // The session to the server died; reconnect rather than killing
+            // the worker, so subworkers and in-flight work survive the blip.
+            state_rpc.schedule_reconnect(server_address, listen_address, ready_file_rpc);
+        }));
     }
 
     pub fn on_subworker_connection(&self, stream: UnixStream) {
@@ -251,11 +654,14 @@ impl StateRef {
         let upstream =
             ::subworker_capnp::subworker_upstream::ToClient::new(SubworkerUpstreamImpl::new(self))
                 .from_server::<::capnp_rpc::Server>();
+        let admission = self.get().admission.clone();
+        admission.inc();
         let rpc_system = new_rpc_system(stream, Some(upstream.client));
-        let inner = self.get();
-        inner
-            .handle
-            .spawn(rpc_system.map_err(|e| error!("RPC error: {:?}", e)));
+        let conn = CountedConn {
+            inner: rpc_system.map_err(|e| error!("RPC error: {:?}", e)),
+            admission,
+        };
+        self.get().handle.spawn(conn);
     }
 
 
@@ -282,8 +688,11 @@ impl StateRef {
         let listener = UnixListener::bind(self.get().subworker_listen_path(), &handle)
             .expect("Cannot initialize unix socket for subworkers");
         let state = self.clone();
-        let future = listener
-            .incoming()
+        let admission = self.get().admission.clone();
+        let future = ThrottledIncoming {
+                inner: listener.incoming(),
+                admission,
+            }
             .for_each(move |(stream, addr)| {
                           state.on_subworker_connection(stream);
                           Ok(())
@@ -296,15 +705,35 @@ impl StateRef {
         // -- Start python subworker (FOR TESTING PURPOSE)
         //start_python_subworker(self);
 
-        // --- Start listening TCP/IP for worker2worker communications ----
-        let listener = TcpListener::bind(&listen_address, &handle).unwrap();
-        let port = listener.local_addr().unwrap().port();
-        listen_address.set_port(port); // Since listen port may be 0, we need to update the real port
-        info!("Start listening on port={}", port);
+        // --- Start listening for worker2worker communications, over
+        //     whichever transport this worker was configured with ----
+        let transport = self.get().transport;
+        let incoming: Box<Stream<Item = (RpcStream, SocketAddr), Error = ::std::io::Error>> =
+            match transport {
+                Transport::Tcp => {
+                    let listener = TcpListener::bind(&listen_address, &handle).unwrap();
+                    let port = listener.local_addr().unwrap().port();
+                    listen_address.set_port(port); // Since listen port may be 0, we need to update the real port
+                    Box::new(listener
+                                 .incoming()
+                                 .map(|(stream, addr)| (RpcStream::Tcp(stream), addr)))
+                }
+                Transport::Quic => {
+                    let (listener, port) = transport::listen(&handle, &listen_address).unwrap();
+                    listen_address.set_port(port);
+                    Box::new(listener.map(|(conn, addr)| {
+                        conn.incoming_bi().map(move |stream| (RpcStream::Quic(stream), addr))
+                    }).flatten())
+                }
+            };
+        info!("Start listening on port={} (transport={:?})", listen_address.port(), transport);
 
         let state = self.clone();
-        let future = listener
-            .incoming()
+        let admission = self.get().admission.clone();
+        let future = ThrottledIncoming {
+                inner: incoming,
+                admission,
+            }
             .for_each(move |(stream, addr)| {
                           state.on_connection(stream, addr);
                           Ok(())
@@ -315,22 +744,109 @@ impl StateRef {
         handle.spawn(future);
 
         // --- Start connection to server ----
-        let core1 = self.clone();
         let ready_file = ready_file.map(|f| f.to_string());
+        self.connect_to_server(server_address, listen_address, ready_file);
+    }
+
+    /// Dials the server and, once connected, registers as a worker. Unlike
+    /// a one-shot attempt, a failure to connect (or a later RPC error) does
+    /// not bring the worker down: it is handed to `schedule_reconnect`
+    /// instead, which retries with exponential backoff.
+    fn connect_to_server(&self,
+                        server_address: SocketAddr,
+                        listen_address: SocketAddr,
+                        ready_file: Option<String>) {
+        // A fresh attempt is starting, so the next failure is free to
+        // schedule its own reconnect again.
+        self.get().reconnect_scheduled.set(false);
+
+        let transport = self.get().transport;
+        let handle = self.get().handle.clone();
+        let state = self.clone();
+        let state_err = self.clone();
+        let ready_file_err = ready_file.clone();
+
         info!("Connecting to server addr={}", server_address);
-        let connect = TcpStream::connect(&server_address, &handle)
+        let connect = connect_rpc_stream(transport, &server_address, &handle)
             .and_then(move |stream| {
-                          core1.on_connected_to_server(stream, listen_address, ready_file);
-                          Ok(())
-                      })
-            .map_err(|e| {
-                         error!("Connecting to server failed: {}", e);
-                         exit(1);
-                     });
+                state.on_connected_to_server(stream, server_address, listen_address, ready_file);
+                Ok(())
+            })
+            .or_else(move |e| {
+                error!("Connecting to server failed: {}", e);
+                state_err.schedule_reconnect(server_address, listen_address, ready_file_err);
+                Ok(())
+            });
         handle.spawn(connect);
     }
 
+    /// Waits out the current backoff delay, doubling it (capped at
+    /// `RECONNECT_MAX_DELAY_MS`, with jitter) for the next failure, then
+    /// retries `connect_to_server`.
+    fn schedule_reconnect(&self,
+                         server_address: SocketAddr,
+                         listen_address: SocketAddr,
+                         ready_file: Option<String>) {
+        if self.get().reconnect_scheduled.replace(true) {
+            // The other future racing on this same connection (registration
+            // vs. raw RPC system) already scheduled a reconnect for this
+            // disconnect; don't double the backoff for one real failure.
+            return;
+        }
+
+        let state = self.clone();
+        let (timer, handle, delay_ms) = {
+            let inner = self.get();
+            let delay_ms = inner.reconnect_delay_ms.get();
+            let next = (delay_ms * 2).min(RECONNECT_MAX_DELAY_MS);
+            inner.reconnect_delay_ms.set(next);
+            (inner.timer.clone(), inner.handle.clone(), delay_ms)
+        };
+        let jitter_ms = ::rand::thread_rng().gen_range(0, delay_ms / 2 + 1);
+        info!("Retrying server connection in {}ms", delay_ms + jitter_ms);
+
+        handle.spawn(timer.sleep(Duration::from_millis(delay_ms + jitter_ms))
+                          .then(move |_| {
+                state.connect_to_server(server_address, listen_address, ready_file);
+                Ok(())
+            }));
+    }
+
+    /// Resets the reconnect backoff to its base delay; called once
+    /// registration with the server completes.
+    fn reset_reconnect_backoff(&self) {
+        self.get().reconnect_delay_ms.set(RECONNECT_BASE_DELAY_MS);
+    }
+
     pub fn turn(&self) {
         // Now do nothing
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admission_trips_at_maxconn_and_clears_below_it() {
+        let admission = Admission::new(100);
+        assert_eq!(admission.low_watermark, 36);
+        assert!(!admission.at_capacity());
+
+        for _ in 0..100 {
+            admission.inc();
+        }
+        assert!(admission.at_capacity());
+
+        admission.dec();
+        assert!(!admission.at_capacity());
+    }
+
+    #[test]
+    fn admission_low_watermark_never_underflows_for_small_maxconn() {
+        // maxconn smaller than LOW_WATERMARK_MARGIN must saturate to 0
+        // rather than panicking on subtraction overflow.
+        let admission = Admission::new(4);
+        assert_eq!(admission.low_watermark, 0);
+    }
+}